@@ -5,41 +5,118 @@ use std::path::{Path, PathBuf};
 use anyhow::Result;
 use clap::{Arg, crate_authors, crate_version};
 use env_logger;
-use log::{debug, info, LevelFilter};
+use log::{debug, info, warn, LevelFilter};
 use nitro_cli::build_from_docker;
 use tempdir::TempDir;
 
 use eif_tools::*;
 
+mod dockerfile;
+mod engine;
+mod harden;
+mod output;
+mod remote;
+
+use dockerfile::DockerfileOptions;
+use engine::Engine;
+use harden::SecurityOptions;
+use output::OutputFormat;
+
 const DEFAULT_RESOURCE_PATH: &str = "/usr/share/nitro_enclaves/blobs/";
 
 /// Create a temporary directory used for creating a docker image.
-fn setup_docker_dir(elf_path: &Path) -> Result<TempDir> {
-    const DOCKERFILE: &str = "
-        FROM scratch
-        COPY enclave .
-        CMD [\"./enclave\"]
-    ";
+///
+/// This is engine-agnostic: the directory it produces is fed to either Docker or Podman
+/// unchanged, the only difference between the two lives in [`Engine::env_overrides`].
+fn setup_docker_dir(elf_path: &Path, dockerfile_options: &DockerfileOptions) -> Result<TempDir> {
     info!("Setting up docker directory");
+    let rendered_dockerfile = dockerfile_options.render()?;
     let docker_dir = TempDir::new("elf2eif_docker_dir")?;
     std::fs::copy(elf_path, docker_dir.path().join("enclave").into_os_string())?;
+    for file in &dockerfile_options.pre_build_files {
+        let file_name = file.file_name()
+            .ok_or_else(|| anyhow::anyhow!("`{}` has no file name", file.display()))?;
+        std::fs::copy(file, docker_dir.path().join(file_name))?;
+    }
     let mut dockerfile = File::create(docker_dir.path().join("Dockerfile"))?;
-    writeln!(dockerfile, "{}", DOCKERFILE)?;
+    writeln!(dockerfile, "{}", rendered_dockerfile)?;
     Ok(docker_dir)
 }
 
-fn run(input_path: &Path, output_path: &str, signing_certificate: &Option<String>, private_key: &Option<String>, resource_path: &PathBuf) {
+/// Copy every file in `context_files` into `volume`, then read each one back out and compare it
+/// byte-for-byte against the original, so a truncated or corrupted transfer to a remote engine
+/// is caught here rather than surfacing as a confusing build failure later.
+fn stage_and_verify(volume: &remote::RemoteVolume, context_files: &[PathBuf]) -> Result<()> {
+    let verify_dir = TempDir::new("elf2eif_verify")?;
+    for file in context_files {
+        let file_name = file.file_name().and_then(|n| n.to_str())
+            .ok_or_else(|| anyhow::anyhow!("`{}` has no file name", file.display()))?;
+        volume.copy_in(file, file_name)?;
+        let roundtrip_path = verify_dir.path().join(file_name);
+        volume.copy_out(file_name, &roundtrip_path)?;
+        let original = std::fs::read(file)?;
+        let roundtrip = std::fs::read(&roundtrip_path)?;
+        if original != roundtrip {
+            return Err(anyhow::anyhow!("`{}` did not round-trip through volume `{}` intact", file.display(), volume.name()));
+        }
+    }
+    Ok(())
+}
+
+fn run(input_path: &Path, output_path: &str, signing_certificate: &Option<String>, private_key: &Option<String>, resource_path: &PathBuf, engine: &Engine, remote: bool, keep_volume: bool, dockerfile_options: &DockerfileOptions, security: &SecurityOptions, output_format: OutputFormat, measurements_output: &Option<PathBuf>, expected_measurements: &Option<PathBuf>) {
     println!("Converting elf file `{}` to eif, please wait", input_path.display());
+    info!("Building with container engine `{}`", engine);
+    for (var, value) in engine.env_overrides() {
+        std::env::set_var(var, value);
+    }
 
-    let docker_dir = match setup_docker_dir(input_path) {
+    let docker_dir = match setup_docker_dir(input_path, dockerfile_options) {
         Ok(d) => d,
         Err(e) => {
             println!("Could create docker image from elf file: {:?}", e);
             std::process::exit(1);
         }
     };
+
+    // `build_from_docker` only ever bind-mounts a local directory, so the build itself always
+    // runs against our local `docker_dir`, remote engine or not. `--remote` additionally stages
+    // (and verifies) a copy of that build context in a named volume on the engine host, so it's
+    // actually reachable there; see `remote` for why.
+    if remote && security.is_active() {
+        warn!("--uid/--gid/--security-opt only harden the --remote helper containers that shuttle \
+               files into the build volume; the enclave build container itself is created by \
+               build_from_docker, which accepts no user or seccomp option, so it still runs \
+               unhardened and its intermediates are still root-owned");
+    }
+    let mut volume = if remote {
+        let tag = format!("{}", std::process::id());
+        let volume = match remote::RemoteVolume::create(engine, &tag, security) {
+            Ok(v) => v,
+            Err(e) => {
+                println!("Could not create remote build volume: {:?}", e);
+                std::process::exit(1);
+            }
+        };
+        let context_files = std::fs::read_dir(docker_dir.path())
+            .and_then(|entries| entries.map(|e| e.map(|e| e.path())).collect::<std::io::Result<Vec<_>>>());
+        let context_files = match context_files {
+            Ok(files) => files,
+            Err(e) => {
+                println!("Could not enumerate build context `{:?}`: {:?}", docker_dir.path(), e);
+                std::process::exit(1);
+            }
+        };
+        if let Err(e) = stage_and_verify(&volume, &context_files) {
+            println!("Could not stage build context into remote build volume: {:?}", e);
+            drop(volume);
+            std::process::exit(1);
+        }
+        Some(volume)
+    } else {
+        None
+    };
     let docker_dir_path = docker_dir.path().to_str().map(|s| s.to_string());
-    debug!("Created docker dir `{:?}`", docker_dir_path);
+    debug!("Building from `{:?}`", docker_dir_path);
 
     let (_output_file, measurements) = match build_from_docker(&resource_path, "elf2eif", &docker_dir_path, output_path, &signing_certificate, &private_key) {
         Ok((o, m)) => {
@@ -53,12 +130,30 @@ fn run(input_path: &Path, output_path: &str, signing_certificate: &Option<String
                 debug!("Could not clean up docker directory `{:?}`", docker_dir_path)
             }
             println!("Building eif failed with {:?}", e);
+            drop(volume);
             std::process::exit(1);
         }
     };
+    if keep_volume {
+        if let Some(volume) = volume.as_mut() {
+            volume.keep();
+            info!("Keeping remote build volume `{}`", volume.name());
+        }
+    }
 
     println!("Enclave Image successfully created: `{}`", output_path);
-    println!("{:#?}", measurements);
+    if let Err(e) = output::report_measurements(&measurements, output_format, measurements_output.as_deref()) {
+        println!("Could not report measurements: {:?}", e);
+        drop(volume);
+        std::process::exit(1);
+    }
+    if let Some(expected_path) = expected_measurements {
+        if let Err(e) = output::verify_measurements(&measurements, expected_path) {
+            println!("{:?}", e);
+            drop(volume);
+            std::process::exit(1);
+        }
+    }
 }
 
 fn main() {
@@ -102,8 +197,99 @@ fn main() {
             .value_name("FILE")
             .validator_os(readable_file)
             .help("Path to private key for signed enclaves"))
+        .arg(Arg::with_name("container-engine")
+            .long("container-engine")
+            .value_name("ENGINE")
+            .possible_values(&["docker", "podman"])
+            .help("Container engine to build with [env: FTXVME_CONTAINER_ENGINE] [default: auto-detected, preferring docker]"))
+        .arg(Arg::with_name("remote")
+            .long("remote")
+            .help("Stage (and verify) the build context in a named volume on the engine reached via DOCKER_HOST; the image build itself still always runs against the local build context, since build_from_docker cannot target a volume [env: FTXVME_REMOTE]"))
+        .arg(Arg::with_name("keep-volume")
+            .long("keep-volume")
+            .requires("remote")
+            .help("Don't remove the remote build volume once the build finishes; list it with list-volumes [env: FTXVME_KEEP_VOLUME]"))
+        .arg(Arg::with_name("base-image")
+            .long("base-image")
+            .value_name("IMAGE")
+            .conflicts_with("dockerfile")
+            .help("Base image to use instead of `scratch` in the generated Dockerfile"))
+        .arg(Arg::with_name("dockerfile")
+            .long("dockerfile")
+            .value_name("FILE")
+            .validator_os(readable_file)
+            .help("Full Dockerfile template to use instead of the built-in one; `{{ENCLAVE}}` is replaced with the enclave binary's name"))
+        .arg(Arg::with_name("pre-build")
+            .long("pre-build")
+            .value_name("FILE")
+            .multiple(true)
+            .number_of_values(1)
+            .validator_os(readable_file)
+            .help("Additional file to copy into the build context for the Dockerfile to use, e.g. a shared library or CA bundle"))
+        .arg(Arg::with_name("uid")
+            .long("uid")
+            .value_name("UID")
+            .requires("gid")
+            .requires("remote")
+            .help("User ID the --remote helper containers run as; build_from_docker manages the actual build container itself and accepts no such option [env: FTXVME_UID]"))
+        .arg(Arg::with_name("gid")
+            .long("gid")
+            .value_name("GID")
+            .requires("uid")
+            .requires("remote")
+            .help("Group ID the --remote helper containers run as; build_from_docker manages the actual build container itself and accepts no such option [env: FTXVME_GID]"))
+        .arg(Arg::with_name("security-opt")
+            .long("security-opt")
+            .value_name("OPT")
+            .possible_values(&["none"])
+            .requires("remote")
+            .help("Pass `none` to disable the seccomp profile applied to --remote's helper containers [env: FTXVME_SECURITY_OPT]"))
+        .arg(Arg::with_name("output-format")
+            .long("output-format")
+            .value_name("FORMAT")
+            .possible_values(&["text", "json"])
+            .default_value("text")
+            .help("How to report the produced measurements"))
+        .arg(Arg::with_name("measurements-file")
+            .long("measurements-file")
+            .value_name("FILE")
+            .help("Where to write --output-format json measurements; defaults to stdout"))
+        .arg(Arg::with_name("expected-measurements")
+            .long("expected-measurements")
+            .value_name("FILE")
+            .validator_os(readable_file)
+            .help("Fail with a non-zero exit code unless the produced measurements match this reference JSON file"))
+        .subcommand(clap::SubCommand::with_name("list-volumes")
+            .about("List build volumes created by this tool"))
+        .subcommand(clap::SubCommand::with_name("remove-volumes")
+            .about("Remove one or more build volumes by name")
+            .arg(Arg::with_name("name")
+                .required(true)
+                .multiple(true)
+                .help("Name of a volume to remove")))
+        .subcommand(clap::SubCommand::with_name("prune-volumes")
+            .about("Remove every build volume created by this tool"))
         .get_matches();
 
+    if let Some(subcommand) = args.subcommand_name() {
+        let engine = match Engine::resolve(args.value_of("container-engine")) {
+            Ok(e) => e,
+            Err(e) => {
+                println!("Could not determine which container engine to use: {:?}", e);
+                std::process::exit(1);
+            }
+        };
+        let result = match args.subcommand_matches(subcommand) {
+            Some(matches) => run_volume_subcommand(subcommand, matches, &engine),
+            None => unreachable!("clap guarantees matches for a named subcommand"),
+        };
+        if let Err(e) = result {
+            println!("{:?}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let verbose = args.is_present("verbose");
     let input_path = args.value_of("elffile").map(|p| Path::new(p)).expect("Validated readable file");
     let output_path = args.value_of("eiffile").expect("Validated string");
@@ -111,6 +297,46 @@ fn main() {
     let private_key = args.value_of("private-key").map(|k| k.to_string());
     let resource_path = args.value_of("resource-path").unwrap_or(DEFAULT_RESOURCE_PATH);
     let resource_path = Path::new(resource_path).to_path_buf();
+    let engine = match Engine::resolve(args.value_of("container-engine")) {
+        Ok(e) => e,
+        Err(e) => {
+            println!("Could not determine which container engine to use: {:?}", e);
+            std::process::exit(1);
+        }
+    };
+    let remote = args.is_present("remote") || std::env::var(remote::REMOTE_ENV_VAR).is_ok();
+    let keep_volume = args.is_present("keep-volume") || std::env::var(remote::KEEP_VOLUME_ENV_VAR).is_ok();
+    let uid = parse_id(args.value_of("uid"), harden::UID_ENV_VAR);
+    let gid = parse_id(args.value_of("gid"), harden::GID_ENV_VAR);
+    let security_opt = args.value_of("security-opt").map(|s| s.to_string())
+        .or_else(|| std::env::var(harden::SECURITY_OPT_ENV_VAR).ok());
+    if !remote && (uid.is_some() || gid.is_some() || security_opt.is_some()) {
+        // `clap`'s `requires("remote")` catches this for the CLI flags, but --uid/--gid/
+        // --security-opt can also come in through their env vars, which clap never sees.
+        println!("--uid/--gid/--security-opt (and their env vars) only affect the --remote helper containers; build_from_docker accepts no container options for the actual build");
+        std::process::exit(1);
+    }
+    let security = match SecurityOptions::new(uid, gid, security_opt.as_deref()) {
+        Ok(s) => s,
+        Err(e) => {
+            println!("Could not set up container hardening: {:?}", e);
+            std::process::exit(1);
+        }
+    };
+    let dockerfile_options = DockerfileOptions {
+        base_image: args.value_of("base-image").map(|i| i.to_string()),
+        template: args.value_of("dockerfile").map(PathBuf::from),
+        pre_build_files: args.values_of("pre-build").map(|v| v.map(PathBuf::from).collect()).unwrap_or_default(),
+    };
+    let output_format = match OutputFormat::parse(args.value_of("output-format").expect("Has a default value")) {
+        Ok(f) => f,
+        Err(e) => {
+            println!("{:?}", e);
+            std::process::exit(1);
+        }
+    };
+    let measurements_output = args.value_of("measurements-file").map(PathBuf::from);
+    let expected_measurements = args.value_of("expected-measurements").map(PathBuf::from);
     let mut logger = env_logger::Builder::from_default_env();
     let logger = logger.format(|buf, record| writeln!(buf, "{}", record.args()));
     if verbose {
@@ -119,7 +345,38 @@ fn main() {
         logger.filter_level(LevelFilter::Error).init();
     }
 
-    run(input_path, output_path, &signing_certificate, &private_key, &resource_path);
+    run(input_path, output_path, &signing_certificate, &private_key, &resource_path, &engine, remote, keep_volume, &dockerfile_options, &security, output_format, &measurements_output, &expected_measurements);
+}
+
+/// Parse a `--uid`/`--gid` value, falling back to its environment variable.
+fn parse_id(arg: Option<&str>, env_var: &str) -> Option<u32> {
+    arg.map(|s| s.to_string())
+        .or_else(|| std::env::var(env_var).ok())
+        .and_then(|s| s.parse().ok())
+}
+
+/// Handle the `list-volumes`/`remove-volumes`/`prune-volumes` subcommands.
+fn run_volume_subcommand(name: &str, matches: &clap::ArgMatches, engine: &Engine) -> Result<()> {
+    match name {
+        "list-volumes" => {
+            for volume in remote::list_volumes(engine)? {
+                println!("{}", volume);
+            }
+            Ok(())
+        }
+        "remove-volumes" => {
+            for volume in matches.values_of("name").expect("Required argument") {
+                remote::remove_volume(engine, volume)?;
+            }
+            Ok(())
+        }
+        "prune-volumes" => {
+            let removed = remote::prune_volumes(engine)?;
+            println!("Removed {} volume(s)", removed);
+            Ok(())
+        }
+        other => unreachable!("Unknown subcommand `{}`", other),
+    }
 }
 
 #[cfg(test)]
@@ -148,6 +405,9 @@ mod tests {
         let eif_path = workdir.path().join("a.eif");
         let eif_path = eif_path.to_str().expect("Valid unicode");
         let resource_path = PathBuf::from("./tests/binaries/x86_64/");
-        super::run(&elf_path, &eif_path, &None, &None, &resource_path);
+        let engine = super::engine::Engine::resolve(None).expect("No container engine available");
+        let dockerfile_options = super::dockerfile::DockerfileOptions::none();
+        let security = super::harden::SecurityOptions::new(None, None, None).expect("Can write seccomp profile");
+        super::run(&elf_path, &eif_path, &None, &None, &resource_path, &engine, false, false, &dockerfile_options, &security, super::output::OutputFormat::Text, &None, &None);
     }
 }
@@ -0,0 +1,107 @@
+//! Custom Dockerfile templates and pre-build file injection.
+//!
+//! Mirrors `cross`'s custom `Dockerfile`/`PreBuild` mechanism: by default we still emit the
+//! built-in `FROM scratch` Dockerfile, but a user can override the base image, supply a whole
+//! template, or ask for extra files (shared libraries, CA bundles, data files) to be staged
+//! into the build context alongside the enclave binary.
+
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context, Result};
+
+/// Placeholder substituted with the enclave binary's in-context file name inside a
+/// user-supplied template.
+pub const ENCLAVE_PLACEHOLDER: &str = "{{ENCLAVE}}";
+
+/// How to assemble the Dockerfile and build context for this build.
+pub struct DockerfileOptions {
+    /// Replaces `scratch` in the built-in template; ignored once `template` is set.
+    pub base_image: Option<String>,
+    /// Full Dockerfile template; `{{ENCLAVE}}` is substituted with the enclave binary's name.
+    pub template: Option<PathBuf>,
+    /// Extra files copied into the build context alongside the enclave binary and Dockerfile,
+    /// under their original file name, for the template to `COPY` as it sees fit.
+    pub pre_build_files: Vec<PathBuf>,
+}
+
+impl DockerfileOptions {
+    pub fn none() -> DockerfileOptions {
+        DockerfileOptions { base_image: None, template: None, pre_build_files: Vec::new() }
+    }
+
+    /// Render the Dockerfile contents for this build, validating that it has a runnable
+    /// entrypoint (`CMD` or `ENTRYPOINT`) before handing it to the build path.
+    pub fn render(&self) -> Result<String> {
+        let rendered = match &self.template {
+            Some(path) => {
+                let contents = fs::read_to_string(path)
+                    .with_context(|| format!("Could not read Dockerfile template `{}`", path.display()))?;
+                contents.replace(ENCLAVE_PLACEHOLDER, "enclave")
+            }
+            None => {
+                let base_image = self.base_image.as_deref().unwrap_or("scratch");
+                let pre_build_copies: String = self
+                    .pre_build_files
+                    .iter()
+                    .map(|path| format!("COPY {} .\n        ", file_name(path)))
+                    .collect();
+                format!(
+                    "
+        FROM {base_image}
+        {pre_build_copies}COPY enclave .
+        CMD [\"./enclave\"]
+    ",
+                    base_image = base_image,
+                    pre_build_copies = pre_build_copies,
+                )
+            }
+        };
+        if !rendered.contains("CMD") && !rendered.contains("ENTRYPOINT") {
+            return Err(anyhow!(
+                "Dockerfile has no CMD or ENTRYPOINT instruction, the enclave would never run"
+            ));
+        }
+        Ok(rendered)
+    }
+}
+
+/// File name a pre-build file is staged under in the build context, i.e. what a template must
+/// `COPY` to pick it up.
+fn file_name(path: &PathBuf) -> String {
+    path.file_name().and_then(|name| name.to_str()).unwrap_or_default().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_template_uses_base_image() {
+        let mut opts = DockerfileOptions::none();
+        opts.base_image = Some("alpine:3.18".to_string());
+        let rendered = opts.render().unwrap();
+        assert!(rendered.contains("FROM alpine:3.18"));
+    }
+
+    #[test]
+    fn default_template_copies_pre_build_files_before_the_enclave() {
+        let mut opts = DockerfileOptions::none();
+        opts.pre_build_files = vec![PathBuf::from("/staging/libfoo.so"), PathBuf::from("/staging/ca-bundle.crt")];
+        let rendered = opts.render().unwrap();
+        let copy_libfoo = rendered.find("COPY libfoo.so .").expect("libfoo.so is not COPYd");
+        let copy_ca_bundle = rendered.find("COPY ca-bundle.crt .").expect("ca-bundle.crt is not COPYd");
+        let copy_enclave = rendered.find("COPY enclave .").expect("enclave is not COPYd");
+        assert!(copy_libfoo < copy_enclave && copy_ca_bundle < copy_enclave);
+    }
+
+    #[test]
+    fn template_without_entrypoint_is_rejected() {
+        let dir = tempdir::TempDir::new("dockerfile-test").unwrap();
+        let path = dir.path().join("Dockerfile");
+        std::fs::write(&path, "FROM scratch\nCOPY enclave .\n").unwrap();
+        let mut opts = DockerfileOptions::none();
+        opts.template = Some(path);
+        assert!(opts.render().is_err());
+    }
+}
@@ -0,0 +1,137 @@
+//! Container-engine abstraction so builds can run against Docker or Podman.
+//!
+//! `nitro_cli::build_from_docker` always talks to whatever sits behind `DOCKER_HOST` (or the
+//! default local socket if that's unset). Docker needs no help there, but rootless Podman
+//! exposes a Docker-API-compatible socket that isn't picked up automatically, so the engine
+//! just needs to point `DOCKER_HOST` at it before the build call. That keeps `setup_docker_dir`
+//! and the build invocation itself identical across engines.
+
+use std::env;
+use std::fmt;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+
+/// Environment variable used to force a specific engine, overriding auto-detection.
+pub const ENGINE_ENV_VAR: &str = "FTXVME_CONTAINER_ENGINE";
+
+/// A container engine capable of building the EIF image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Engine {
+    Docker,
+    Podman,
+}
+
+impl Engine {
+    /// Binary name used to invoke this engine on `PATH`.
+    fn binary(&self) -> &'static str {
+        match self {
+            Engine::Docker => "docker",
+            Engine::Podman => "podman",
+        }
+    }
+
+    /// Parse an engine name given on the command line or via `FTXVME_CONTAINER_ENGINE`.
+    pub fn parse(name: &str) -> Result<Engine> {
+        match name.to_ascii_lowercase().as_str() {
+            "docker" => Ok(Engine::Docker),
+            "podman" => Ok(Engine::Podman),
+            other => Err(anyhow!("Unknown container engine `{}`, expected `docker` or `podman`", other)),
+        }
+    }
+
+    /// Resolve which engine to use: an explicit `--container-engine` value always wins,
+    /// otherwise fall back to `FTXVME_CONTAINER_ENGINE`, otherwise auto-detect by probing
+    /// `PATH` in order: `docker`, then `podman`.
+    pub fn resolve(explicit: Option<&str>) -> Result<Engine> {
+        if let Some(name) = explicit {
+            return Engine::parse(name);
+        }
+        if let Ok(name) = env::var(ENGINE_ENV_VAR) {
+            return Engine::parse(&name);
+        }
+        Engine::detect()
+    }
+
+    /// Probe `PATH` for a usable engine binary, preferring `docker` then `podman` so existing
+    /// Docker-based setups keep behaving exactly as before.
+    fn detect() -> Result<Engine> {
+        for engine in [Engine::Docker, Engine::Podman] {
+            if which(engine.binary()).is_some() {
+                return Ok(engine);
+            }
+        }
+        Err(anyhow!(
+            "Could not find `docker` or `podman` on PATH; install one of them or pass --container-engine"
+        ))
+    }
+
+    /// Environment variables that must be set before delegating to
+    /// `nitro_cli::build_from_docker` so its internal client talks to this engine.
+    ///
+    /// Never overrides a `DOCKER_HOST` the user already set: that's also how `--remote`
+    /// selects a remote engine, so clobbering it here would silently redirect a remote build
+    /// back to the local rootless Podman socket.
+    pub fn env_overrides(&self) -> Vec<(&'static str, String)> {
+        match self {
+            Engine::Docker => Vec::new(),
+            Engine::Podman => {
+                if env::var_os("DOCKER_HOST").is_some() {
+                    Vec::new()
+                } else {
+                    vec![("DOCKER_HOST", podman_socket_uri())]
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Engine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.binary())
+    }
+}
+
+/// Address of the rootless Podman API socket, following podman's own `$XDG_RUNTIME_DIR`
+/// convention (falling back to the root runtime directory when unset).
+fn podman_socket_uri() -> String {
+    let runtime_dir = env::var("XDG_RUNTIME_DIR").unwrap_or_else(|_| "/run/user/0".to_string());
+    format!("unix://{}/podman/podman.sock", runtime_dir)
+}
+
+fn which(binary: &str) -> Option<PathBuf> {
+    let path = env::var_os("PATH")?;
+    env::split_paths(&path).find_map(|dir| {
+        let candidate = dir.join(binary);
+        if candidate.is_file() {
+            Some(candidate)
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_known_engine_names() {
+        assert_eq!(Engine::parse("docker").unwrap(), Engine::Docker);
+        assert_eq!(Engine::parse("Podman").unwrap(), Engine::Podman);
+        assert!(Engine::parse("bollard").is_err());
+    }
+
+    #[test]
+    fn explicit_choice_wins_over_auto_detection() {
+        assert_eq!(Engine::resolve(Some("podman")).unwrap(), Engine::Podman);
+    }
+
+    #[test]
+    fn podman_does_not_clobber_an_existing_docker_host() {
+        env::set_var("DOCKER_HOST", "tcp://remote-builder:2375");
+        let overrides = Engine::Podman.env_overrides();
+        env::remove_var("DOCKER_HOST");
+        assert!(overrides.is_empty());
+    }
+}
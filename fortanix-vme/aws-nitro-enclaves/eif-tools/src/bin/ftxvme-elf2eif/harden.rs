@@ -0,0 +1,103 @@
+//! Hardening for the containers this tool spawns directly: a non-root UID/GID and a restrictive
+//! seccomp profile, both overridable for hosts where they get in the way.
+//!
+//! The only containers this tool spawns itself are the `--remote` helper containers in
+//! [`super::remote`] that shuttle files into and out of a build volume; the actual enclave
+//! build runs inside whatever container `nitro_cli::build_from_docker` creates, and that
+//! function has no parameter for a user or a seccomp profile. So `SecurityOptions` is only
+//! ever meaningful together with `--remote`, and `main` rejects it otherwise rather than
+//! silently hardening nothing. Even with `--remote`, `run` logs a warning via
+//! [`SecurityOptions::is_active`] that the build container itself is still unhardened, since
+//! that's easy to miss from the CLI help text alone.
+
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use tempdir::TempDir;
+
+/// Env vars mirroring `--uid`/`--gid`/`--security-opt`.
+pub const UID_ENV_VAR: &str = "FTXVME_UID";
+pub const GID_ENV_VAR: &str = "FTXVME_GID";
+pub const SECURITY_OPT_ENV_VAR: &str = "FTXVME_SECURITY_OPT";
+
+/// The seccomp profile applied by default to every container this tool spawns. It denies
+/// syscalls that an `elf2eif` build has no business making, while explicitly allow-listing
+/// `clone`/`clone3` so process forking keeps working under both Docker and Podman.
+const SECCOMP_PROFILE: &str = include_str!("resources/seccomp-elf2eif.json");
+
+/// User and syscall hardening to apply to the containers this tool spawns.
+pub struct SecurityOptions {
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    /// `None` disables the seccomp profile entirely (the `--security-opt none` escape hatch);
+    /// `Some` keeps the seccomp profile file alive for as long as a build needs it.
+    seccomp_profile: Option<(TempDir, PathBuf)>,
+}
+
+impl SecurityOptions {
+    /// Build the security options for a run. `security_opt` mirrors the `--security-opt` flag:
+    /// pass `"none"` to skip the bundled seccomp profile, anything else keeps it enabled.
+    pub fn new(uid: Option<u32>, gid: Option<u32>, security_opt: Option<&str>) -> Result<SecurityOptions> {
+        let seccomp_profile = match security_opt {
+            Some("none") => None,
+            _ => Some(write_profile()?),
+        };
+        Ok(SecurityOptions { uid, gid, seccomp_profile })
+    }
+
+    /// `--user uid:gid` arguments for a `docker`/`podman run` invocation, if a UID/GID was given.
+    pub fn user_args(&self) -> Vec<String> {
+        match (self.uid, self.gid) {
+            (Some(uid), Some(gid)) => vec!["--user".to_string(), format!("{}:{}", uid, gid)],
+            _ => Vec::new(),
+        }
+    }
+
+    /// `--security-opt seccomp=<path>` arguments, if the profile hasn't been disabled.
+    pub fn security_opt_args(&self) -> Vec<String> {
+        match &self.seccomp_profile {
+            Some((_dir, path)) => vec!["--security-opt".to_string(), format!("seccomp={}", path.display())],
+            None => Vec::new(),
+        }
+    }
+
+    /// Whether any hardening applies, i.e. whether `user_args`/`security_opt_args` would apply
+    /// anything to a container. This is true by default, since the seccomp profile is on unless
+    /// disabled with `--security-opt none`. Callers use this to warn that the hardening only ever
+    /// reaches the `--remote` helper containers, never the build container itself.
+    pub fn is_active(&self) -> bool {
+        !self.user_args().is_empty() || !self.security_opt_args().is_empty()
+    }
+}
+
+fn write_profile() -> Result<(TempDir, PathBuf)> {
+    let dir = TempDir::new("elf2eif_seccomp")?;
+    let path = dir.path().join("seccomp-elf2eif.json");
+    fs::write(&path, SECCOMP_PROFILE).with_context(|| format!("Could not write seccomp profile to `{}`", path.display()))?;
+    Ok((dir, path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn security_opt_none_disables_seccomp() {
+        let options = SecurityOptions::new(None, None, Some("none")).unwrap();
+        assert!(options.security_opt_args().is_empty());
+    }
+
+    #[test]
+    fn default_enables_seccomp_and_user() {
+        let options = SecurityOptions::new(Some(1000), Some(1000), None).unwrap();
+        assert_eq!(options.user_args(), vec!["--user".to_string(), "1000:1000".to_string()]);
+        assert!(!options.security_opt_args().is_empty());
+    }
+
+    #[test]
+    fn is_active_requires_something_disabled_by_security_opt_none() {
+        let options = SecurityOptions::new(None, None, Some("none")).unwrap();
+        assert!(!options.is_active());
+    }
+}
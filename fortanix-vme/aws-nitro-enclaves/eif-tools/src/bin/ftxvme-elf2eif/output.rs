@@ -0,0 +1,125 @@
+//! Machine-readable measurement output and verification against a reference set of PCRs.
+//!
+//! `nitro_cli`'s measurements already derive `Serialize` (it needs that for its own `--output
+//! json` support), so turning them into JSON is just a `serde_json` call away; the work here is
+//! picking where that JSON goes and comparing it against a reference set when asked to.
+
+use std::fmt::Debug;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+use serde_json::Value;
+
+/// How to report the measurements produced by a build.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// The existing `{:#?}` human-readable dump.
+    Text,
+    /// `serde_json`-serialized measurements (PCR0/1/2, and PCR8 for signed enclaves).
+    Json,
+}
+
+impl OutputFormat {
+    pub fn parse(name: &str) -> Result<OutputFormat> {
+        match name {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            other => Err(anyhow!("Unknown output format `{}`, expected `text` or `json`", other)),
+        }
+    }
+}
+
+/// Print or write `measurements` according to `format`. With `OutputFormat::Json` and no
+/// `dest`, the JSON goes to stdout instead of a file.
+pub fn report_measurements<M: Serialize + Debug>(measurements: &M, format: OutputFormat, dest: Option<&Path>) -> Result<()> {
+    match format {
+        OutputFormat::Text => {
+            println!("{:#?}", measurements);
+            Ok(())
+        }
+        OutputFormat::Json => {
+            let json = serde_json::to_string_pretty(measurements).context("Could not serialize measurements to JSON")?;
+            match dest {
+                Some(path) => fs::write(path, json)
+                    .with_context(|| format!("Could not write measurements to `{}`", path.display())),
+                None => {
+                    println!("{}", json);
+                    Ok(())
+                }
+            }
+        }
+    }
+}
+
+/// Compare `measurements` against a reference set of PCRs read from `expected_path`, erroring
+/// out with the diff if any value differs. `expected_path` only needs to hold the PCRs the
+/// caller cares about (e.g. just `PCR0`) — fields `actual` has but `expected` doesn't are not
+/// considered a mismatch, so a reference file can stay a deliberate subset.
+pub fn verify_measurements<M: Serialize>(measurements: &M, expected_path: &Path) -> Result<()> {
+    let actual: Value = serde_json::to_value(measurements).context("Could not serialize measurements to JSON")?;
+    let expected_contents = fs::read_to_string(expected_path)
+        .with_context(|| format!("Could not read expected measurements `{}`", expected_path.display()))?;
+    let expected: Value = serde_json::from_str(&expected_contents)
+        .with_context(|| format!("`{}` is not valid JSON", expected_path.display()))?;
+    let expected = expected.as_object()
+        .ok_or_else(|| anyhow!("`{}` must be a JSON object", expected_path.display()))?;
+    let mismatches: Vec<String> = expected.iter()
+        .filter(|(key, value)| actual.get(key.as_str()) != Some(*value))
+        .map(|(key, value)| format!("  {}: expected {}, got {}", key, value, actual.get(key).unwrap_or(&Value::Null)))
+        .collect();
+    if !mismatches.is_empty() {
+        return Err(anyhow!(
+            "Measurements do not match `{}`:\n{}",
+            expected_path.display(),
+            mismatches.join("\n"),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Serialize;
+
+    #[derive(Serialize, Debug)]
+    struct FakeMeasurements {
+        pcr0: &'static str,
+    }
+
+    #[derive(Serialize, Debug)]
+    struct FakeMeasurementsWithExtraPcr {
+        pcr0: &'static str,
+        pcr1: &'static str,
+    }
+
+    #[test]
+    fn verify_measurements_accepts_a_match() {
+        let dir = tempdir::TempDir::new("output-test").unwrap();
+        let path = dir.path().join("expected.json");
+        std::fs::write(&path, r#"{"pcr0":"abc"}"#).unwrap();
+        let measurements = FakeMeasurements { pcr0: "abc" };
+        assert!(verify_measurements(&measurements, &path).is_ok());
+    }
+
+    #[test]
+    fn verify_measurements_rejects_a_mismatch() {
+        let dir = tempdir::TempDir::new("output-test").unwrap();
+        let path = dir.path().join("expected.json");
+        std::fs::write(&path, r#"{"pcr0":"different"}"#).unwrap();
+        let measurements = FakeMeasurements { pcr0: "abc" };
+        assert!(verify_measurements(&measurements, &path).is_err());
+    }
+
+    #[test]
+    fn verify_measurements_only_checks_fields_present_in_the_reference() {
+        let dir = tempdir::TempDir::new("output-test").unwrap();
+        let path = dir.path().join("expected.json");
+        // The reference only pins pcr0; pcr1 can be anything without failing the check.
+        std::fs::write(&path, r#"{"pcr0":"abc"}"#).unwrap();
+        let measurements = FakeMeasurementsWithExtraPcr { pcr0: "abc", pcr1: "whatever" };
+        assert!(verify_measurements(&measurements, &path).is_ok());
+    }
+}
@@ -0,0 +1,191 @@
+//! Staging and verifying the build context in a named data volume on a remote engine.
+//!
+//! `nitro_cli::build_from_docker` only ever bind-mounts a *local* directory and exposes no
+//! parameter that would let it read from a volume instead, so when the engine is reached over
+//! `DOCKER_HOST` (e.g. `tcp://` or `ssh://`) the build itself still has to run against our local
+//! `setup_docker_dir` output — `--remote` does not and cannot make the enclave build itself
+//! target a non-shared-filesystem engine. What it buys is a way to get the build context onto
+//! that remote host regardless, and to confirm it arrived intact, which is useful in its own
+//! right (e.g. to pre-warm a cache volume, or just to prove the engine is reachable): `copy_in`/
+//! `copy_out` stream file bytes over the engine client's stdin/stdout rather than bind-mounting
+//! local paths, since a bind mount only resolves on whatever host `DOCKER_HOST` actually points
+//! at, which for `tcp://`/`ssh://` is not this machine. The enclave ELF and generated Dockerfile
+//! are staged this way, then read back out and byte-compared against the originals so a
+//! truncated or corrupted transfer is caught here rather than surfacing as a confusing build
+//! failure later. The volume is scope-guarded: it's removed once the run finishes unless the
+//! caller asks to keep it around (`--keep-volume`/`FTXVME_KEEP_VOLUME`) for the
+//! `list-volumes`/`remove-volumes`/`prune-volumes` subcommands to manage later.
+
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use anyhow::{anyhow, Context, Result};
+use log::debug;
+
+use super::engine::Engine;
+use super::harden::SecurityOptions;
+
+/// Env var to opt into the remote/volume-based transfer path.
+pub const REMOTE_ENV_VAR: &str = "FTXVME_REMOTE";
+
+/// Env var mirroring `--keep-volume`.
+pub const KEEP_VOLUME_ENV_VAR: &str = "FTXVME_KEEP_VOLUME";
+
+/// Prefix given to every volume this tool creates, so `list-volumes`/`remove-volumes`/
+/// `prune-volumes` can find them again without touching unrelated volumes on the host.
+pub const VOLUME_PREFIX: &str = "ftxvme-build-";
+
+/// Image used for the throwaway helper container that shuttles files in and out of a volume.
+const HELPER_IMAGE: &str = "busybox";
+
+/// A data volume created on `engine`, removed on drop unless [`RemoteVolume::keep`] was called.
+pub struct RemoteVolume<'a> {
+    engine: &'a Engine,
+    name: String,
+    keep: bool,
+    security: &'a SecurityOptions,
+}
+
+impl<'a> RemoteVolume<'a> {
+    /// Create a new, uniquely named volume on `engine`. `security` hardens the helper
+    /// containers this volume uses to shuttle files in and out.
+    pub fn create(engine: &'a Engine, tag: &str, security: &'a SecurityOptions) -> Result<RemoteVolume<'a>> {
+        let name = format!("{}{}", VOLUME_PREFIX, tag);
+        run_engine(engine, &[s("volume"), s("create"), name.clone()])
+            .with_context(|| format!("Could not create volume `{}`", name))?;
+        Ok(RemoteVolume { engine, name, keep: false, security })
+    }
+
+    /// Name of the volume as created on the engine.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Keep the volume around after this guard is dropped, instead of removing it, so it can be
+    /// found and removed later with the volume subcommands. Later builds do not reuse it: each
+    /// run stages into a volume of its own.
+    pub fn keep(&mut self) {
+        self.keep = true;
+    }
+
+    /// Copy the local file `src` into the volume, under `dest_name`, by streaming its bytes over
+    /// the engine client's stdin rather than bind-mounting `src`'s parent directory — see the
+    /// module docs for why a bind mount can't reach a genuinely remote engine.
+    pub fn copy_in(&self, src: &Path, dest_name: &str) -> Result<()> {
+        let contents = fs::read(src).with_context(|| format!("Could not read `{}`", src.display()))?;
+        let mut args = vec![s("run"), s("--rm"), s("-i")];
+        args.extend(self.security.user_args());
+        args.extend(self.security.security_opt_args());
+        args.extend([
+            s("-v"), format!("{}:/dest", self.name),
+            s(HELPER_IMAGE),
+            s("sh"), s("-c"), format!("cat > /dest/{}", dest_name),
+        ]);
+        run_engine_with_stdin(self.engine, &args, &contents)
+            .with_context(|| format!("Could not copy `{}` into volume `{}`", src.display(), self.name))
+    }
+
+    /// Copy `src_name` out of the volume to the local path `dest`, by streaming its bytes over
+    /// the engine client's stdout rather than bind-mounting `dest`'s parent directory.
+    pub fn copy_out(&self, src_name: &str, dest: &Path) -> Result<()> {
+        let mut args = vec![s("run"), s("--rm")];
+        args.extend(self.security.user_args());
+        args.extend(self.security.security_opt_args());
+        args.extend([
+            s("-v"), format!("{}:/src", self.name),
+            s(HELPER_IMAGE),
+            s("cat"), format!("/src/{}", src_name),
+        ]);
+        let contents = run_engine_capture_stdout(self.engine, &args)
+            .with_context(|| format!("Could not copy `{}` out of volume `{}`", src_name, self.name))?;
+        fs::write(dest, contents).with_context(|| format!("Could not write `{}`", dest.display()))
+    }
+}
+
+impl<'a> Drop for RemoteVolume<'a> {
+    fn drop(&mut self) {
+        if self.keep {
+            return;
+        }
+        if let Err(e) = run_engine(self.engine, &[s("volume"), s("rm"), self.name.clone()]) {
+            debug!("Could not clean up volume `{}`: {:?}", self.name, e);
+        }
+    }
+}
+
+fn s(value: &str) -> String {
+    value.to_string()
+}
+
+fn run_engine(engine: &Engine, args: &[String]) -> Result<()> {
+    let status = Command::new(engine.to_string())
+        .args(args)
+        .status()
+        .with_context(|| format!("Could not invoke `{} {}`", engine, args.join(" ")))?;
+    if !status.success() {
+        return Err(anyhow!("`{} {}` exited with {}", engine, args.join(" "), status));
+    }
+    Ok(())
+}
+
+/// Run `engine` with `args`, writing `input` to its stdin once spawned. Used to stream local
+/// file bytes into a container without a host bind mount.
+fn run_engine_with_stdin(engine: &Engine, args: &[String], input: &[u8]) -> Result<()> {
+    let mut child = Command::new(engine.to_string())
+        .args(args)
+        .stdin(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Could not invoke `{} {}`", engine, args.join(" ")))?;
+    child.stdin.take().expect("stdin was piped").write_all(input)
+        .with_context(|| format!("Could not write to `{} {}`'s stdin", engine, args.join(" ")))?;
+    let status = child.wait().with_context(|| format!("Could not wait for `{} {}`", engine, args.join(" ")))?;
+    if !status.success() {
+        return Err(anyhow!("`{} {}` exited with {}", engine, args.join(" "), status));
+    }
+    Ok(())
+}
+
+/// Run `engine` with `args` and return its stdout bytes. Used to stream a file's bytes back out
+/// of a container without a host bind mount.
+fn run_engine_capture_stdout(engine: &Engine, args: &[String]) -> Result<Vec<u8>> {
+    let output = Command::new(engine.to_string())
+        .args(args)
+        .output()
+        .with_context(|| format!("Could not invoke `{} {}`", engine, args.join(" ")))?;
+    if !output.status.success() {
+        return Err(anyhow!("`{} {}` exited with {}", engine, args.join(" "), output.status));
+    }
+    Ok(output.stdout)
+}
+
+/// List every volume this tool has created on `engine`.
+pub fn list_volumes(engine: &Engine) -> Result<Vec<String>> {
+    let output = Command::new(engine.to_string())
+        .args(&["volume", "ls", "--format", "{{.Name}}"])
+        .output()
+        .with_context(|| format!("Could not list volumes via `{}`", engine))?;
+    if !output.status.success() {
+        return Err(anyhow!("`{} volume ls` exited with {}", engine, output.status));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|name| name.starts_with(VOLUME_PREFIX))
+        .map(|name| name.to_string())
+        .collect())
+}
+
+/// Remove a single volume by name.
+pub fn remove_volume(engine: &Engine, name: &str) -> Result<()> {
+    run_engine(engine, &[s("volume"), s("rm"), s(name)])
+}
+
+/// Remove every volume this tool has created on `engine`, returning how many were removed.
+pub fn prune_volumes(engine: &Engine) -> Result<usize> {
+    let names = list_volumes(engine)?;
+    for name in &names {
+        remove_volume(engine, name)?;
+    }
+    Ok(names.len())
+}
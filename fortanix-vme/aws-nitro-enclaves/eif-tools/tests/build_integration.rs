@@ -0,0 +1,98 @@
+//! Integration tests that exercise the real build path against whatever container engine is
+//! available locally. These spin up real containers, so unlike the crate's unit tests they're
+//! skipped outright when neither docker nor podman is on `PATH`.
+
+use std::fs;
+use std::io::Write;
+use std::process::{Command, Output};
+
+use tempdir::TempDir;
+
+fn container_engine_available() -> bool {
+    ["docker", "podman"]
+        .iter()
+        .any(|bin| Command::new(bin).arg("--version").output().map(|o| o.status.success()).unwrap_or(false))
+}
+
+fn create_elf(workdir: &std::path::Path) -> std::path::PathBuf {
+    let elf_path = workdir.join("a.elf");
+    let mut elf = fs::File::create(&elf_path).expect("Can't create elf");
+    writeln!(elf, "Hello world!").expect("Can't write elf");
+    elf_path
+}
+
+fn elf2eif(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_ftxvme-elf2eif"))
+        .args(args)
+        .output()
+        .expect("Could not run ftxvme-elf2eif")
+}
+
+#[test]
+fn build_measurements_are_deterministic_across_runs() {
+    if !container_engine_available() {
+        eprintln!("Skipping: no container engine on PATH");
+        return;
+    }
+
+    let workdir = TempDir::new("elf2eif-integration").expect("Can't create workdir");
+    let elf_path = create_elf(workdir.path());
+
+    let mut measurements = Vec::new();
+    for i in 0..2 {
+        let eif_path = workdir.path().join(format!("a{}.eif", i));
+        let measurements_path = workdir.path().join(format!("measurements{}.json", i));
+        let output = elf2eif(&[
+            "-i", elf_path.to_str().unwrap(),
+            "-o", eif_path.to_str().unwrap(),
+            "--output-format", "json",
+            "--measurements-file", measurements_path.to_str().unwrap(),
+        ]);
+        assert!(output.status.success(), "build failed: {}", String::from_utf8_lossy(&output.stderr));
+        let contents = fs::read_to_string(&measurements_path).expect("Could not read measurements");
+        let value: serde_json::Value = serde_json::from_str(&contents).expect("Measurements are not valid JSON");
+        measurements.push(value);
+    }
+
+    assert_eq!(measurements[0], measurements[1], "measurements are not deterministic across identical builds");
+}
+
+#[test]
+fn build_fails_on_measurement_mismatch() {
+    if !container_engine_available() {
+        eprintln!("Skipping: no container engine on PATH");
+        return;
+    }
+
+    let workdir = TempDir::new("elf2eif-integration").expect("Can't create workdir");
+    let elf_path = create_elf(workdir.path());
+
+    // Build once for real, so the reference measurements we check against come from an actual
+    // run rather than a hand-typed value, and then corrupt just PCR0 in it. That way the test
+    // below is only ever satisfied by verify_measurements catching a genuine PCR value
+    // mismatch, not by the reference happening to have a different shape than the real output.
+    let baseline_eif_path = workdir.path().join("baseline.eif");
+    let measurements_path = workdir.path().join("measurements.json");
+    let baseline_output = elf2eif(&[
+        "-i", elf_path.to_str().unwrap(),
+        "-o", baseline_eif_path.to_str().unwrap(),
+        "--output-format", "json",
+        "--measurements-file", measurements_path.to_str().unwrap(),
+    ]);
+    assert!(baseline_output.status.success(), "baseline build failed: {}", String::from_utf8_lossy(&baseline_output.stderr));
+    let contents = fs::read_to_string(&measurements_path).expect("Could not read measurements");
+    let mut measurements: serde_json::Value = serde_json::from_str(&contents).expect("Measurements are not valid JSON");
+    let pcr0 = measurements["PCR0"].as_str().expect("Measurements have a PCR0 field").to_string();
+    measurements["PCR0"] = serde_json::Value::String(format!("not-{}", pcr0));
+    let expected_path = workdir.path().join("expected.json");
+    fs::write(&expected_path, serde_json::to_string(&measurements).unwrap()).expect("Can't write expected measurements");
+
+    let eif_path = workdir.path().join("a.eif");
+    let output = elf2eif(&[
+        "-i", elf_path.to_str().unwrap(),
+        "-o", eif_path.to_str().unwrap(),
+        "--output-format", "json",
+        "--expected-measurements", expected_path.to_str().unwrap(),
+    ]);
+    assert!(!output.status.success(), "build should fail on a measurement mismatch");
+}